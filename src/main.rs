@@ -1,17 +1,41 @@
 //! This example illustrates loading scenes from files.
-use bevy::{ecs::system::SystemState, math::vec4, prelude::*, utils::Uuid, winit::WinitSettings};
+use bevy::{
+    ecs::system::SystemState, math::vec4, prelude::*, scene::InstanceId, tasks::IoTaskPool,
+    utils::HashSet, utils::Uuid, winit::WinitSettings,
+};
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_mod_picking::prelude::*;
+use std::any::TypeId;
+use std::fs::File;
+use std::io::Write;
 
 fn main() {
     App::new()
-        .add_plugins(DefaultPlugins)
+        .add_plugins(DefaultPlugins.set(AssetPlugin {
+            watch_for_changes: true,
+            ..default()
+        }))
         .add_plugins(WorldInspectorPlugin::new())
         .add_plugins(DefaultPickingPlugins)
         .register_type::<Uuid>()
+        .register_type::<SerializedMaterial>()
+        .register_type::<MeshShape>()
+        .register_type::<SceneAmbientLight>()
         .insert_resource(WinitSettings::desktop_app())
         .insert_resource(SceneStoreResource::default())
+        .insert_resource(SceneFilter::default())
+        .insert_resource(SceneAmbientLight::default())
+        .insert_resource(ScenePersistentResources::default())
+        .insert_resource(PendingSceneRestore::default())
         .add_systems(Startup, (sys_setup_ui, sys_setup_scene))
+        .add_systems(
+            Update,
+            (
+                sys_on_reparent_restored_scene,
+                sys_log_reloaded_app_ids,
+                sys_apply_scene_ambient_light,
+            ),
+        )
         .run();
 }
 
@@ -35,7 +59,7 @@ const HIGHLIGHT_TINT: Highlight<StandardMaterial> = Highlight {
 // example. The `FromWorld` trait determines how your component is constructed when it loads.
 // For simple use cases you can just implement the `Default` trait (which automatically implements
 // `FromWorld`). The simplest registered component just needs these three derives:
-#[derive(Component, Reflect, Default)]
+#[derive(Component, Reflect, Default, Clone, Copy)]
 #[reflect(Component)] // this tells the reflect derive to also reflect component behaviors
 struct AppId(Uuid);
 
@@ -48,15 +72,156 @@ enum SceneStoreResource {
         target_appid: AppId,
     },
     Stored {
-        /// Parent of the stored scene object
-        parent_appid: AppId,
+        /// Parent of the stored scene object, if it had one. `None` for a
+        /// top-level entity like the cube, which isn't reparented on restore.
+        parent_appid: Option<AppId>,
         // App Id of the stored scene object
         target_appId: AppId,
         // The dynamic scene to add/remove from scene
         scene: DynamicScene,
+        // Path of the `.scn.ron` file this scene was (or is being) written
+        // to, relative to `assets/`, if the write has been kicked off.
+        path: Option<String>,
     },
 }
 
+/// Example of an app resource opted into scene persistence: an ambient
+/// light tint, applied to the real `AmbientLight` resource by
+/// `sys_apply_scene_ambient_light`. Demonstrates resource round-tripping
+/// through `DynamicSceneBuilder::extract_resources`. `SceneStoreResource`
+/// itself does not derive `Reflect` and is not captured this way.
+#[derive(Resource, Reflect, Default, Clone)]
+#[reflect(Resource)]
+struct SceneAmbientLight(Color);
+
+/// Resources the user has marked as "scene-persistent" — each must derive
+/// `Reflect` with `#[reflect(Resource)]` and be registered in the
+/// `AppTypeRegistry`, same as a component going through `SceneFilter`.
+#[derive(Resource)]
+struct ScenePersistentResources(Vec<TypeId>);
+
+impl Default for ScenePersistentResources {
+    fn default() -> Self {
+        Self(vec![TypeId::of::<SceneAmbientLight>()])
+    }
+}
+
+/// Restricts which component types `sys_on_to_dynamic_scene` hands off to
+/// `DynamicSceneBuilder`, so a picked object's transient picking/highlight
+/// state doesn't get dragged along for the ride.
+///
+/// Mirrors `DynamicSceneBuilder`'s own allow/deny model: in `Allow` mode only
+/// the listed types are extracted, in `Deny` mode everything except the
+/// listed types is extracted.
+#[derive(Resource, Clone)]
+enum SceneFilter {
+    Allow(HashSet<TypeId>),
+    Deny(HashSet<TypeId>),
+}
+
+impl Default for SceneFilter {
+    fn default() -> Self {
+        // Deny the picking/highlight components that shouldn't persist
+        // across a store/restore cycle, plus the raw mesh/material handles:
+        // those reflect to an opaque asset index that's meaningless once
+        // reloaded into a fresh `Assets<T>` table, so `SerializedMaterial`
+        // and `MeshShape` stand in for them instead.
+        let mut deny = HashSet::new();
+        deny.insert(TypeId::of::<PickSelection>());
+        deny.insert(TypeId::of::<Highlight<StandardMaterial>>());
+        deny.insert(TypeId::of::<Handle<Mesh>>());
+        deny.insert(TypeId::of::<Handle<StandardMaterial>>());
+        Self::Deny(deny)
+    }
+}
+
+impl SceneFilter {
+    /// Applies this filter's allow/deny list to a `DynamicSceneBuilder`,
+    /// driving its `allow_by_id`/`deny_by_id` calls from our runtime set of
+    /// `TypeId`s rather than a fixed list of `allow::<T>()`/`deny::<T>()`.
+    fn apply(&self, builder: &mut DynamicSceneBuilder) {
+        match self {
+            Self::Allow(types) => {
+                for &type_id in types {
+                    builder.allow_by_id(type_id);
+                }
+            }
+            Self::Deny(types) => {
+                for &type_id in types {
+                    builder.deny_by_id(type_id);
+                }
+            }
+        }
+    }
+}
+
+/// Reflectable, serializable stand-in for a `Handle<StandardMaterial>`.
+/// `Handle<T>` reflects to an opaque asset index that's worthless once the
+/// scene is reloaded into a new `Assets<StandardMaterial>` table, so this
+/// captures the handful of fields the scratchpad's materials actually use.
+#[derive(Component, Reflect, Default, Clone)]
+#[reflect(Component)]
+struct SerializedMaterial {
+    base_color: Color,
+    perceptual_roughness: f32,
+    metallic: f32,
+}
+
+impl From<&StandardMaterial> for SerializedMaterial {
+    fn from(material: &StandardMaterial) -> Self {
+        Self {
+            base_color: material.base_color,
+            perceptual_roughness: material.perceptual_roughness,
+            metallic: material.metallic,
+        }
+    }
+}
+
+impl From<&SerializedMaterial> for StandardMaterial {
+    fn from(serialized: &SerializedMaterial) -> Self {
+        StandardMaterial {
+            base_color: serialized.base_color,
+            perceptual_roughness: serialized.perceptual_roughness,
+            metallic: serialized.metallic,
+            ..default()
+        }
+    }
+}
+
+/// Reflectable description of the primitive meshes spawned in
+/// `sys_setup_scene`, used in place of the opaque `Handle<Mesh>` when
+/// round-tripping a picked entity through a `DynamicScene`.
+#[derive(Component, Reflect, Clone)]
+#[reflect(Component)]
+enum MeshShape {
+    Cube { size: f32 },
+    Plane { size: f32 },
+    Torus { radius: f32, ring_radius: f32 },
+}
+
+impl Default for MeshShape {
+    fn default() -> Self {
+        Self::Cube { size: 1.0 }
+    }
+}
+
+impl From<&MeshShape> for Mesh {
+    fn from(shape: &MeshShape) -> Self {
+        match *shape {
+            MeshShape::Cube { size } => Mesh::from(shape::Cube { size }),
+            MeshShape::Plane { size } => Mesh::from(shape::Plane::from_size(size)),
+            MeshShape::Torus {
+                radius,
+                ring_radius,
+            } => Mesh::from(shape::Torus {
+                radius,
+                ring_radius,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
 fn sys_setup_ui(mut commands: Commands) {
     commands
         .spawn(NodeBundle {
@@ -97,12 +262,29 @@ fn sys_setup_ui(mut commands: Commands) {
                     },
                     ..Default::default()
                 })
+                .insert(On::<Pointer<Click>>::run(sys_on_from_dynamic_scene))
                 .with_children(|parent| {
                     parent.spawn(TextBundle::from_section(
                         "From Dynamic Scene",
                         TextStyle::default(),
                     ));
                 });
+            parent
+                .spawn(ButtonBundle {
+                    background_color: Color::rgb_u8(50, 50, 50).into(),
+                    style: Style {
+                        padding: UiRect::all(Val::Px(10.)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(On::<Pointer<Click>>::run(sys_on_toggle_scene_filter))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Toggle Filter Mode",
+                        TextStyle::default(),
+                    ));
+                });
         });
 }
 
@@ -127,6 +309,7 @@ fn sys_setup_scene(
             ..default()
         },
         AppId::default(),
+        MeshShape::Plane { size: 5.0 },
         PickableBundle::default(),
         HIGHLIGHT_TINT,
     ));
@@ -140,6 +323,7 @@ fn sys_setup_scene(
                 ..default()
             },
             AppId::default(),
+            MeshShape::Cube { size: 1.0 },
             PickableBundle::default(),
             HIGHLIGHT_TINT,
         ))
@@ -156,6 +340,10 @@ fn sys_setup_scene(
                     ..Default::default()
                 },
                 AppId::default(),
+                MeshShape::Torus {
+                    radius: 0.1,
+                    ring_radius: 0.5,
+                },
                 PickableBundle::default(),
                 HIGHLIGHT_TINT,
             ));
@@ -173,14 +361,337 @@ fn sys_setup_scene(
 }
 
 fn sys_on_to_dynamic_scene(world: &mut World) {
-    let mut sys_state: SystemState<(ResMut<SceneStoreResource>, Query<(Entity, &AppId)>)> =
-        SystemState::new(world);
+    let mut sys_state: SystemState<(
+        Query<(Entity, &AppId, Option<&Parent>, &PickSelection)>,
+        Query<&Children>,
+        Res<SceneFilter>,
+    )> = SystemState::new(world);
+    let (q_entities, q_children, scene_filter) = sys_state.get(world);
+    let scene_filter = scene_filter.clone();
+
+    let Some((root, root_app_id, parent_entity)) = q_entities
+        .iter()
+        .find(|(.., selection)| selection.is_selected)
+        .map(|(entity, app_id, parent, _)| (entity, *app_id, parent.map(|p| p.get())))
+    else {
+        // Nothing is currently picked.
+        return;
+    };
+
+    // The parent (if any) lives outside the extracted subtree, so its
+    // `AppId` is the key we need to reconnect the stored scene to the live
+    // hierarchy later. A top-level entity like the cube has no `Parent` at
+    // all, which is a valid root to store, not a failure.
+    let parent_app_id = parent_entity.and_then(|parent_entity| {
+        q_entities
+            .iter()
+            .find_map(|(entity, app_id, ..)| (entity == parent_entity).then_some(*app_id))
+    });
+
+    // Depth-first walk of the picked entity's hierarchy so descendants (e.g.
+    // the cube's child torus) are captured in the same scene.
+    let mut subtree = Vec::new();
+    let mut stack = vec![root];
+    while let Some(entity) = stack.pop() {
+        subtree.push(entity);
+        if let Ok(children) = q_children.get(entity) {
+            stack.extend(children.iter().rev().copied());
+        }
+    }
+
+    // Stand in `SerializedMaterial` for each extracted entity's
+    // `Handle<StandardMaterial>` so its appearance survives the round trip
+    // (`MeshShape` is already attached at spawn time in `sys_setup_scene`).
+    let mut material_sys_state: SystemState<(
+        Query<&Handle<StandardMaterial>>,
+        Res<Assets<StandardMaterial>>,
+    )> = SystemState::new(world);
+    let (q_materials, materials) = material_sys_state.get(world);
+    let serialized_materials: Vec<(Entity, SerializedMaterial)> = subtree
+        .iter()
+        .filter_map(|&entity| {
+            let material = materials.get(q_materials.get(entity).ok()?)?;
+            Some((entity, SerializedMaterial::from(material)))
+        })
+        .collect();
+    for (entity, serialized_material) in serialized_materials {
+        world.entity_mut(entity).insert(serialized_material);
+    }
+
+    // `DynamicSceneBuilder` would otherwise also extract the root's `Parent`
+    // component, which points at an entity outside the extracted subtree and
+    // would dangle once the scene is spawned in isolation. Drop it for the
+    // duration of the extraction and restore it afterwards.
+    world.entity_mut(root).remove_parent();
+    let mut builder = DynamicSceneBuilder::from_world(world);
+    scene_filter.apply(&mut builder);
+    for &entity in &subtree {
+        builder.extract_entity(entity);
+    }
+    // Re-use the same allow/deny filtering machinery for resources: once
+    // switched to allow-mode, only the types registered in
+    // `ScenePersistentResources` are captured by `extract_resources`.
+    for type_id in &world.resource::<ScenePersistentResources>().0 {
+        builder.allow_resource_by_id(*type_id);
+    }
+    builder.extract_resources();
+    let scene = builder.build();
+    if let Some(parent_entity) = parent_entity {
+        world.entity_mut(root).set_parent(parent_entity);
+    }
+
+    // Persist the scene to disk so it can be hot-reloaded through the
+    // `AssetServer` later; the filename is deterministic from the root's
+    // `AppId` so the path is known before the write finishes.
+    let relative_path = format!("scenes/{}.scn.ron", root_app_id.0);
+    let type_registry = world.resource::<AppTypeRegistry>().clone();
+    // Only remember the path if the write was actually kicked off; otherwise
+    // the restore path must fall back to the in-memory scene instead of
+    // trying to load a file that was never written.
+    let path = match scene.serialize_ron(&type_registry) {
+        Ok(serialized_scene) if cfg!(not(target_arch = "wasm32")) => {
+            let absolute_path = format!("assets/{relative_path}");
+            #[cfg(not(target_arch = "wasm32"))]
+            IoTaskPool::get()
+                .spawn(async move {
+                    if let Some(parent) = std::path::Path::new(&absolute_path).parent() {
+                        let _ = std::fs::create_dir_all(parent);
+                    }
+                    File::create(&absolute_path)
+                        .and_then(|mut file| file.write(serialized_scene.as_bytes()))
+                        .expect("Error while writing scene to file");
+                })
+                .detach();
+            Some(relative_path)
+        }
+        _ => None,
+    };
+
+    *world.resource_mut::<SceneStoreResource>() = SceneStoreResource::Stored {
+        parent_appid: parent_app_id,
+        target_appId: root_app_id,
+        scene,
+        path,
+    };
+}
+
+/// Swaps `SceneFilter` between its deny-mode default and an allow-mode that
+/// keeps only `AppId`/`Transform`, so `SceneFilter::Allow` actually gets
+/// constructed somewhere rather than just matched on in `SceneFilter::apply`.
+fn sys_on_toggle_scene_filter(mut scene_filter: ResMut<SceneFilter>) {
+    *scene_filter = match &*scene_filter {
+        SceneFilter::Deny(_) => {
+            let mut allow = HashSet::new();
+            allow.insert(TypeId::of::<AppId>());
+            allow.insert(TypeId::of::<Transform>());
+            SceneFilter::Allow(allow)
+        }
+        SceneFilter::Allow(_) => SceneFilter::default(),
+    };
+}
+
+/// Tracks a scene spawned via `SceneSpawner` that still needs to be
+/// reconnected to the live hierarchy: `SceneSpawner` instantiates a scene
+/// over the following frame(s) rather than synchronously, so the resulting
+/// root entity isn't known until `sys_on_reparent_restored_scene` observes
+/// `instance_is_ready`. The handle is kept around so that system can also
+/// read back the scene's extracted resources once it has loaded.
+#[derive(Resource, Default)]
+struct PendingSceneRestore(Option<(InstanceId, Option<Entity>, Handle<DynamicScene>)>);
+
+fn sys_on_from_dynamic_scene(world: &mut World) {
+    let mut sys_state: SystemState<Query<(Entity, &AppId)>> = SystemState::new(world);
+    let q_app_id = sys_state.get(world);
 
-    let (mut scene_sture, q_app_id) = sys_state.get_mut(world);
+    // Peek the stored state (and resolve the live parent entity, if any)
+    // before committing to `mem::take`-ing it out, so a parent that no
+    // longer exists doesn't discard the stored scene and its on-disk path.
+    let (parent_appid, target_appid) = match &*world.resource::<SceneStoreResource>() {
+        SceneStoreResource::Stored {
+            parent_appid,
+            target_appId,
+            ..
+        } => (*parent_appid, *target_appId),
+        // Nothing has been stored yet.
+        _ => return,
+    };
+
+    // A stored root with no parent (e.g. the cube) is simply left top-level
+    // on restore instead of being reparented.
+    let parent_entity = match parent_appid {
+        Some(parent_appid) => {
+            let Some(entity) = q_app_id
+                .iter()
+                .find_map(|(entity, app_id)| (app_id.0 == parent_appid.0).then_some(entity))
+            else {
+                // The live parent this scene was extracted from is gone;
+                // leave the stored scene untouched so this can be retried.
+                return;
+            };
+            Some(entity)
+        }
+        None => None,
+    };
+
+    let SceneStoreResource::Stored { scene, path, .. } =
+        std::mem::take(&mut *world.resource_mut::<SceneStoreResource>())
+    else {
+        unreachable!("already confirmed SceneStoreResource::Stored above")
+    };
+
+    // Upsert: despawn any live entity that already carries this `AppId` so
+    // re-pressing the button restores in place instead of piling up
+    // duplicates. Queried fresh since the scan above borrows `world`
+    // immutably and can't span the `mem::take`'s mutable borrow.
+    let mut upsert_sys_state: SystemState<Query<(Entity, &AppId)>> = SystemState::new(world);
+    let q_app_id = upsert_sys_state.get(world);
+    if let Some(existing) = q_app_id
+        .iter()
+        .find_map(|(entity, app_id)| (app_id.0 == target_appid.0).then_some(entity))
+    {
+        world.entity_mut(existing).despawn_recursive();
+    }
+
+    // Prefer loading the on-disk `.scn.ron` through the `AssetServer`: with
+    // asset change-watching enabled, subsequent edits to that file then
+    // live-reapply to the spawned entity. Fall back to the in-memory scene
+    // if it hasn't been written yet.
+    let handle = match path {
+        Some(path) if std::path::Path::new("assets").join(&path).exists() => {
+            world.resource::<AssetServer>().load(path)
+        }
+        _ => world.resource_mut::<Assets<DynamicScene>>().add(scene),
+    };
+    let instance_id = world
+        .resource_mut::<SceneSpawner>()
+        .spawn_dynamic(handle.clone());
+    world.resource_mut::<PendingSceneRestore>().0 = Some((instance_id, parent_entity, handle));
+}
+
+/// Once `SceneSpawner` finishes instantiating a scene queued by
+/// `sys_on_from_dynamic_scene`, reparents its root entity under the live
+/// entity that matches the stored `parent_appid` (a root that was top-level
+/// when stored has no `parent_appid`, and is left top-level on restore too).
+fn sys_on_reparent_restored_scene(world: &mut World) {
+    let Some((instance_id, parent_entity, scene_handle)) =
+        world.resource::<PendingSceneRestore>().0.clone()
+    else {
+        return;
+    };
+    if !world
+        .resource::<SceneSpawner>()
+        .instance_is_ready(instance_id)
+    {
+        return;
+    }
+
+    let mut sys_state: SystemState<(
+        Res<SceneSpawner>,
+        Query<&Parent>,
+        Query<(
+            Option<&SerializedMaterial>,
+            Option<&MeshShape>,
+            Option<&Handle<StandardMaterial>>,
+            Option<&Handle<Mesh>>,
+        )>,
+    )> = SystemState::new(world);
+    let (scene_spawner, q_parent, q_restored) = sys_state.get(world);
+
+    let mut root_entity = None;
+    let mut assets_to_add = Vec::new();
+    for entity in scene_spawner.iter_instance_entities(instance_id) {
+        if root_entity.is_none() && q_parent.get(entity).is_err() {
+            root_entity = Some(entity);
+        }
+
+        // Recreate the mesh/material assets that `SerializedMaterial`/
+        // `MeshShape` stood in for, since `Handle<T>` itself doesn't
+        // survive serialization.
+        let Ok((serialized_material, mesh_shape, material_handle, mesh_handle)) =
+            q_restored.get(entity)
+        else {
+            continue;
+        };
+        let material = material_handle
+            .is_none()
+            .then_some(serialized_material)
+            .flatten()
+            .map(StandardMaterial::from);
+        let mesh = mesh_handle
+            .is_none()
+            .then_some(mesh_shape)
+            .flatten()
+            .map(Mesh::from);
+        if material.is_some() || mesh.is_some() {
+            assets_to_add.push((entity, material, mesh));
+        }
+    }
+
+    for (entity, material, mesh) in assets_to_add {
+        if let Some(material) = material {
+            let handle = world
+                .resource_mut::<Assets<StandardMaterial>>()
+                .add(material);
+            world.entity_mut(entity).insert(handle);
+        }
+        if let Some(mesh) = mesh {
+            let handle = world.resource_mut::<Assets<Mesh>>().add(mesh);
+            world.entity_mut(entity).insert(handle);
+        }
+    }
+
+    if let (Some(root_entity), Some(parent_entity)) = (root_entity, parent_entity) {
+        world.entity_mut(root_entity).set_parent(parent_entity);
+    }
+
+    // `SceneSpawner` doesn't overwrite a resource that already exists in the
+    // world, so force it via reflection: clone each extracted resource out
+    // of the scene asset, then `ReflectResource::insert` it back in.
+    let type_registry = world.resource::<AppTypeRegistry>().clone();
+    let resources_to_restore: Vec<Box<dyn Reflect>> = world
+        .resource::<Assets<DynamicScene>>()
+        .get(&scene_handle)
+        .map(|dynamic_scene| {
+            dynamic_scene
+                .resources
+                .iter()
+                .map(|resource| resource.clone_value())
+                .collect()
+        })
+        .unwrap_or_default();
+    let registry = type_registry.read();
+    for resource in resources_to_restore {
+        // `clone_value` yields a dynamic proxy, so look the registration up
+        // by the type it represents rather than by the proxy's own type.
+        let Some(reflect_resource) = resource
+            .get_represented_type_info()
+            .and_then(|info| registry.get(info.type_id()))
+            .and_then(|registration| registration.data::<ReflectResource>())
+        else {
+            continue;
+        };
+        reflect_resource.insert(world, &*resource);
+    }
+    drop(registry);
+
+    world.resource_mut::<PendingSceneRestore>().0 = None;
+}
+
+/// Applies `SceneAmbientLight` to the real `AmbientLight` resource, so the
+/// tint captured/restored through scene persistence actually shows up.
+fn sys_apply_scene_ambient_light(
+    scene_ambient_light: Res<SceneAmbientLight>,
+    mut ambient_light: ResMut<AmbientLight>,
+) {
+    ambient_light.color = scene_ambient_light.0;
+}
 
-    // let mut builder = DynamicSceneBuilder::from_world(&world);
-    // builder.extract_entity(entity);
-    // let dynamic_scene = builder.build();
+/// Logs entities whose `AppId` changed this frame, so edits to a stored
+/// scene's `.scn.ron` file are visible as they're hot-reloaded back in.
+fn sys_log_reloaded_app_ids(query: Query<Entity, Changed<AppId>>) {
+    for entity in &query {
+        info!("{entity:?} reloaded from scene file");
+    }
 }
 //
 // fn load_scene_system(mut commands: Commands, asset_server: Res<AssetServer>) {